@@ -8,13 +8,292 @@
 /// コマンドラインオプション処理モジュール
 ///
 
-use std::io::{Write, BufWriter, Error};
+use std::io::{self, Write, BufWriter, Error};
 use std::fs::File;
 
 use clap::Parser;
 use regex::Regex;
-use chrono::{NaiveDateTime, Utc, TimeZone};
+use chrono::{
+    DateTime, FixedOffset, LocalResult, NaiveDateTime, SecondsFormat, TimeZone, Utc
+};
 use chrono_tz::Tz;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::encoder::{self, Encoder, Format, Timestamp};
+
+///
+/// 出力先ライタ
+///
+/// 通常の出力先と、gzip圧縮を行う出力先の双方を透過的に扱うためのラッパー。
+///
+pub enum OutputWriter {
+    /// 無圧縮の出力先
+    Plain(Box<dyn Write>),
+
+    /// gzip圧縮した出力先
+    Gzip(GzEncoder<Box<dyn Write>>),
+}
+
+impl Write for OutputWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            OutputWriter::Plain(w) => w.write(buf),
+            OutputWriter::Gzip(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            OutputWriter::Plain(w) => w.flush(),
+            OutputWriter::Gzip(w) => w.flush(),
+        }
+    }
+}
+
+impl OutputWriter {
+    ///
+    /// 出力の終了処理
+    ///
+    /// # 戻り値
+    /// gzip圧縮を行っている場合は、トレイラーの書き込みを含むエンコーダの終了
+    /// 処理を行う。無圧縮の場合は、バッファのフラッシュのみを行う。
+    ///
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            OutputWriter::Plain(mut w) => w.flush(),
+            OutputWriter::Gzip(w) => {
+                w.finish()?;
+                Ok(())
+            }
+        }
+    }
+}
+
+///
+/// タイムゾーン情報
+///
+/// chrono-tzの地域名（`Asia/Tokyo`等）で解決できたものと、固定オフセットでし
+/// か表現できなかったもの（固定オフセット表記やtzfileのフォールバック）を同一に
+/// 扱うためのラッパー。
+///
+pub(crate) enum ZoneInfo {
+    /// chrono-tzの地域情報
+    Named(Tz),
+
+    /// 固定オフセット
+    Fixed(FixedOffset),
+}
+
+impl ZoneInfo {
+    ///
+    /// ローカル時刻のUNIX時刻(ミリ秒)への変換
+    ///
+    /// # 引数
+    /// `time` - 変換対象のローカル時刻
+    /// `label` - 警告/エラーメッセージに使用する元の文字列
+    ///
+    /// # 戻り値
+    /// 変換に成功した場合はミリ秒単位のUNIX時刻をOk()でラップして返す。`time`
+    /// が存在しない時刻（サマータイム開始時の欠落時間帯）の場合はErr(())を返
+    /// す。
+    ///
+    fn local_to_unix_millis(&self, time: NaiveDateTime, label: &str) -> Result<i64, ()> {
+        match self {
+            ZoneInfo::Named(tz) => {
+                local_result_to_millis(tz.from_local_datetime(&time), label)
+            }
+
+            ZoneInfo::Fixed(off) => {
+                local_result_to_millis(off.from_local_datetime(&time), label)
+            }
+        }
+    }
+
+    ///
+    /// UTC時刻のこのタイムゾーンでのオフセット付き文字列への変換
+    ///
+    /// # 引数
+    /// `utc` - 変換対象のUTC時刻
+    /// `fmt` - 出力形式
+    ///
+    /// # 戻り値
+    /// オフセットを含む文字列表現
+    ///
+    fn format_instant(&self, utc: DateTime<Utc>, fmt: &TimeFormat) -> String {
+        match self {
+            ZoneInfo::Named(tz) => format_with_offset(utc.with_timezone(tz), fmt),
+            ZoneInfo::Fixed(off) => format_with_offset(utc.with_timezone(off), fmt),
+        }
+    }
+}
+
+///
+/// 曖昧・不定なローカル時刻変換結果のUNIX時刻(ミリ秒)への変換
+///
+/// # 引数
+/// `result` - `TimeZone::from_local_datetime`の結果
+/// `label` - 警告/エラーメッセージに使用する元の文字列
+///
+/// # 戻り値
+/// `Single`の場合はその時刻を、`Ambiguous`の場合は最も早い方の候補を採用し、
+/// ミリ秒単位のUNIX時刻をOk()でラップして返す。`None`（存在しない時刻）の場
+/// 合はErr(())を返す。
+///
+fn local_result_to_millis<T: TimeZone>(result: LocalResult<DateTime<T>>, label: &str)
+    -> Result<i64, ()>
+{
+    let local = match result {
+        LocalResult::Single(local) => local,
+
+        LocalResult::Ambiguous(earliest, _latest) => {
+            eprintln!(
+                "Ambiguous local time in file name: {} \
+                 (using the earliest candidate)", label
+            );
+
+            earliest
+        }
+
+        LocalResult::None => {
+            eprintln!("Nonexistent local time in file name: {}", label);
+            return Err(());
+        }
+    };
+
+    let utc = Utc.from_utc_datetime(&local.naive_utc());
+
+    Ok(utc.timestamp_millis())
+}
+
+///
+/// オフセット付き時刻文字列への整形
+///
+/// # 引数
+/// `dt` - 整形対象の時刻
+/// `fmt` - 出力形式
+///
+/// # 戻り値
+/// 出力形式に応じて整形した、タイムゾーンのオフセットを含む文字列
+///
+fn format_with_offset<T: TimeZone>(dt: DateTime<T>, fmt: &TimeFormat) -> String
+where
+    T::Offset: std::fmt::Display,
+{
+    match fmt {
+        TimeFormat::Rfc3339 => dt.to_rfc3339_opts(SecondsFormat::Millis, false),
+        _ => dt.format("%Y-%m-%d %H:%M:%S%.3f %:z").to_string(),
+    }
+}
+
+///
+/// 固定オフセット文字列のパース
+///
+/// `UTC+09:00`、`GMT+9`、`+0900`、`+09:00`のような固定オフセット表記を受け付
+/// ける。
+///
+/// # 引数
+/// `s` - パース対象の文字列
+///
+/// # 戻り値
+/// パースに成功した場合はFixedOffsetをSome()でラップして返す。そうでない場
+/// 合はNoneを返す。
+///
+fn parse_fixed_offset(s: &str) -> Option<FixedOffset> {
+    let body = s.strip_prefix("UTC").or_else(|| s.strip_prefix("GMT")).unwrap_or(s);
+    let re = Regex::new(r"^([+-])(\d{1,2}):?(\d{2})?$").unwrap();
+    let captures = re.captures(body)?;
+
+    let sign = if &captures[1] == "-" { -1 } else { 1 };
+    let hours: i32 = captures[2].parse().ok()?;
+    let mins: i32 = captures.get(3).map_or(0, |m| m.as_str().parse().unwrap_or(0));
+
+    FixedOffset::east_opt(sign * (hours * 3600 + mins * 60))
+}
+
+///
+/// システムのtzfileからの固定オフセットの取得
+///
+/// chrono-tzの地域名にも固定オフセット表記にも一致しない場合のフォールバック
+/// として、`/usr/share/zoneinfo/<name>`（あるいは`name`が絶対パスの場合はそ
+/// のパス自体、それも無ければ`TZ`環境変数が指す地域）のtzfile(TZif)を読み込
+/// み、末尾に埋め込まれたPOSIX TZ文字列（RFC 8536のfooter）から標準時のオフ
+/// セットのみを取り出す。夏時間の規則までは解釈しない。
+///
+/// # 引数
+/// `name` - タイムゾーン名、またはtzfileへのパス
+///
+/// # 戻り値
+/// 取得に成功した場合はFixedOffsetをSome()でラップして返す。そうでない場合
+/// はNoneを返す。
+///
+fn read_tzfile_offset(name: &str) -> Option<FixedOffset> {
+    let path = if std::path::Path::new(name).is_absolute() {
+        name.to_string()
+    } else {
+        format!("/usr/share/zoneinfo/{}", name)
+    };
+
+    let data = std::fs::read(&path).ok().or_else(|| {
+        let tz = std::env::var("TZ").ok()?;
+        let fallback = format!("/usr/share/zoneinfo/{}", tz);
+        let data = std::fs::read(&fallback).ok()?;
+
+        eprintln!(
+            "Timezone '{}' not found at {}; falling back to $TZ ({})",
+            name, path, tz
+        );
+
+        Some(data)
+    })?;
+
+    let text = String::from_utf8_lossy(&data);
+    let mut parts = text.rsplit('\n');
+
+    // 末尾の改行の直後が空文字列になるため読み飛ばす
+    parts.next()?;
+    let footer = parts.next()?;
+
+    posix_tz_std_offset(footer)
+}
+
+///
+/// POSIX TZ文字列からの標準時オフセットの取り出し
+///
+/// # 引数
+/// `spec` - POSIX TZ文字列（例: `JST-9`、`CST6CDT,M3.2.0,M11.1.0`）
+///
+/// # 戻り値
+/// パースに成功した場合はFixedOffsetをSome()でラップして返す。そうでない場
+/// 合はNoneを返す。
+///
+fn posix_tz_std_offset(spec: &str) -> Option<FixedOffset> {
+    let re = Regex::new(r"^(?:[A-Za-z]+|<[^>]+>)([+-]?\d{1,2})(?::(\d{2}))?").unwrap();
+    let captures = re.captures(spec)?;
+
+    let hours: i32 = captures[1].parse().ok()?;
+    let mins: i32 = captures.get(2).map_or(0, |m| m.as_str().parse().unwrap_or(0));
+
+    // POSIX TZ文字列のオフセットは「UTCに戻すために現地時刻に加える値」なの
+    // で、chronoの東経正の規約に合わせるために符号を反転させる
+    let sign = if hours < 0 { -1 } else { 1 };
+    let posix_secs = sign * (hours.abs() * 3600 + mins * 60);
+
+    FixedOffset::east_opt(-posix_secs)
+}
+
+/// タイムスタンプの出力形式
+#[derive(clap::ValueEnum, Clone, Debug)]
+pub enum TimeFormat {
+    /// ミリ秒単位のUNIX時刻
+    EpochMs,
+
+    /// タイムゾーンのオフセット付きRFC3339形式
+    Rfc3339,
+
+    /// タイムゾーンのオフセット付きの読みやすい形式
+    Local,
+}
 
 ///
 /// UNIX時刻への変換
@@ -26,18 +305,14 @@ use chrono_tz::Tz;
 /// # 戻り値
 /// 引数で指定された記録開始時刻を表した文字列と、タイムゾーン情報を元に算出し
 /// たミリ秒単位のUNIX時刻をOk()でラップして返す。指定された、文字列が記録開始
-/// 時刻としてパースできなかった場合はErr(())を返す。
+/// 時刻としてパースできなかった場合はErr(())を返す。また、サマータイムの切り
+/// 替わりにより存在しない時刻の場合もErr(())を返す。時刻が重複する（夏時間終
+/// 了時など）場合は、最も早い方の時刻を採用してOk()で返す。
 ///
-fn try_convert_to_unix_mills(s: String, tz: Tz) -> Result<i64,()>
+fn try_convert_to_unix_mills(s: &str, tz: &ZoneInfo) -> Result<i64,()>
 {
-    match NaiveDateTime::parse_from_str(&s, "%Y%m%d %H%M%S") {
-        Ok(time) => {
-            let local = tz.from_local_datetime(&time).unwrap();
-            let utc = Utc.from_utc_datetime(&local.naive_utc());
-
-            Ok(utc.timestamp_millis())
-        }
-
+    match NaiveDateTime::parse_from_str(s, "%Y%m%d %H%M%S") {
+        Ok(time) => tz.local_to_unix_millis(time, s),
         Err(_) => Err(())
     }
 }
@@ -56,6 +331,30 @@ pub struct Options {
         value_name = "ZONE-NAME", help = "Set applicable time zone")]
     timezone: String,
 
+    /// 出力フォーマット
+    #[arg(short = 'f', long = "format", value_enum, default_value = "csv",
+        help = "Set output format")]
+    format: Format,
+
+    /// タイムスタンプの出力形式
+    #[arg(long = "time-format", value_enum, default_value = "epoch-ms",
+        help = "Set timestamp output format")]
+    time_format: TimeFormat,
+
+    /// 出力をgzip圧縮するかどうか
+    #[arg(short = 'z', long = "compress",
+        help = "Compress output with gzip")]
+    compress: bool,
+
+    /// 瞬間電力列を出力するかどうか
+    #[arg(long = "power", help = "Add an instantaneous power (W) column")]
+    power: bool,
+
+    /// 集計統計をstderrへ出力するかどうか
+    #[arg(long = "summary",
+        help = "Print min/max/mean voltage, current and accumulated energy to stderr")]
+    summary: bool,
+
     /// 入力ファイル名
     #[clap(help = "input file name")]
     input_file: String,
@@ -64,21 +363,35 @@ pub struct Options {
 /// Optionsの実装
 impl Options {
     ///
-    /// タイムゾーン情報の取得
+    /// タイムゾーン情報の解決
     ///
     /// # 戻り値
-    /// コマンドラインオプションで指定されたタイムゾーン文字列が既知のものであ
-    /// る場合は、タイムゾーン情報をOk()でラップして返す。未知のもの（不正なも
-    /// の）の場合はErr(())を返す。
-    ///
-    fn get_timezone(&self) -> Result<Tz, ()> {
-        match self.timezone.parse::<Tz>() {
-            Ok(tz) => Ok(tz),
-            Err(_) => {
-                eprintln!("Invalid timezone string: {}", self.timezone);
-                Err(())
-            }
+    /// コマンドラインオプションで指定されたタイムゾーン文字列がchrono-tzの地
+    /// 域名として解決できた場合は、その地域情報をOk()でラップして返す。解決で
+    /// きない場合は、固定オフセット表記（`UTC+09:00`等）としての解釈、続いて
+    /// システムのtzfileの読み出しをこの順に試みる。いずれも失敗した場合は
+    /// Err(())を返す。
+    ///
+    /// # 注意事項
+    /// 正規表現のコンパイルやtzfileの読み出しを伴うため、エントリ毎ではなく
+    /// `run()`内で一度だけ呼び出し、結果を`get_record_time`/`format_timestamp`
+    /// に使い回すこと。
+    ///
+    pub(crate) fn resolve_timezone(&self) -> Result<ZoneInfo, ()> {
+        if let Ok(tz) = self.timezone.parse::<Tz>() {
+            return Ok(ZoneInfo::Named(tz));
+        }
+
+        if let Some(off) = parse_fixed_offset(&self.timezone) {
+            return Ok(ZoneInfo::Fixed(off));
         }
+
+        if let Some(off) = read_tzfile_offset(&self.timezone) {
+            return Ok(ZoneInfo::Fixed(off));
+        }
+
+        eprintln!("Invalid timezone string: {}", self.timezone);
+        Err(())
     }
 
     ///
@@ -122,16 +435,24 @@ impl Options {
     /// # 注意事項
     /// 出力先は、アペンドモードではなく新規作成モードでオープンされる。このた
     /// め指定されているファイルが既存の場合は、内容が削除されるので注意するこ
-    /// と。
+    /// と。`--compress`指定時、あるいは出力ファイル名が`.gz`で終わる場合は、
+    /// gzip圧縮した出力先を返す。
     ///
-    pub fn get_output_writer(&self) -> Result<Box<dyn Write>, Error> {
-        let io: Box<dyn Write> = if let Some(path) = &self.output_file {
+    pub fn get_output_writer(&self) -> Result<OutputWriter, Error> {
+        let base: Box<dyn Write> = if let Some(path) = &self.output_file {
             Box::new(BufWriter::new(File::create(path)?))
         } else {
             Box::new(BufWriter::new(std::io::stdout()))
         };
 
-        Ok(io)
+        let gzip = self.compress
+            || self.output_file.as_ref().is_some_and(|p| p.ends_with(".gz"));
+
+        if gzip {
+            Ok(OutputWriter::Gzip(GzEncoder::new(base, Compression::default())))
+        } else {
+            Ok(OutputWriter::Plain(base))
+        }
     }
 
     ///
@@ -148,29 +469,97 @@ impl Options {
     ///
     /// 記録時刻の取得
     ///
+    /// # 引数
+    /// `zone` - `resolve_timezone`で解決済みのタイムゾーン情報
+    ///
     /// # 戻り値
     /// 入力ファイル名に記録開始時刻が埋め込まれている場合、その記録開始時刻と
-    /// コマンドラインオプションで指定されたタイムゾーンに則って算出されたミリ
-    /// 秒単位のUNIX時刻をSome()でラップして返す。そうでない場合はNoneを返す。
-    /// コマンドラインオプションで指定されたタイムゾーン文字列が未知のもの（不
-    /// 正なもの）の場合場合もNoneを返す。
-    ///
-    pub fn get_record_time(&self) -> Option<i64> {
-        match (self.get_timeinfo(), self.get_timezone()) {
-            (Some(info), Ok(tz)) => {
-                match try_convert_to_unix_mills(info, tz) {
+    /// `zone`に則って算出されたミリ秒単位のUNIX時刻をSome()でラップして返す。
+    /// そうでない場合はNoneを返す。`zone`が解決できていない場合もNoneを返す。
+    ///
+    pub(crate) fn get_record_time(&self, zone: Result<&ZoneInfo, ()>) -> Option<i64> {
+        match (self.get_timeinfo(), zone) {
+            (Some(info), Ok(zone)) => {
+                match try_convert_to_unix_mills(&info, zone) {
                     Ok(time) => Some(time),
                     Err(_) => {
                         eprintln!("Invalid datetime format in file name: {}",
                                   self.input_file);
                         None
-                    } 
+                    }
                 }
             }
 
             _ => None
         }
     }
+
+    ///
+    /// 出力フォーマットエンコーダの取得
+    ///
+    /// # 引数
+    /// `start_time` - 記録開始時刻（ミリ秒単位のUNIX時刻）
+    ///
+    /// # 戻り値
+    /// コマンドラインオプションで指定されたフォーマットに対応するEncoderトレ
+    /// イトオブジェクト
+    ///
+    pub fn get_encoder(&self, start_time: i64) -> Box<dyn Encoder> {
+        encoder::new_encoder(
+            &self.format, start_time, self.timezone.clone(), self.power
+        )
+    }
+
+    ///
+    /// 電力列を出力するかどうか
+    ///
+    /// # 戻り値
+    /// `--power`が指定されている場合はtrue
+    ///
+    pub fn power_enabled(&self) -> bool {
+        self.power
+    }
+
+    ///
+    /// 集計統計を出力するかどうか
+    ///
+    /// # 戻り値
+    /// `--summary`が指定されている場合はtrue
+    ///
+    pub fn summary_enabled(&self) -> bool {
+        self.summary
+    }
+
+    ///
+    /// タイムスタンプの整形
+    ///
+    /// # 引数
+    /// `ts_millis` - ミリ秒単位のUNIX時刻
+    /// `zone` - `resolve_timezone`で解決済みのタイムゾーン情報
+    ///
+    /// # 戻り値
+    /// コマンドラインオプションで指定された形式に整形したタイムスタンプ。`zone`
+    /// が解決できていない場合は`epoch-ms`指定時と同様にフォールバックする。
+    ///
+    pub(crate) fn format_timestamp(&self, ts_millis: i64, zone: Result<&ZoneInfo, ()>)
+        -> Timestamp
+    {
+        match self.time_format {
+            TimeFormat::EpochMs => Timestamp::Millis(ts_millis),
+
+            TimeFormat::Rfc3339 | TimeFormat::Local => {
+                match zone {
+                    Ok(zone) => {
+                        let utc = Utc.timestamp_millis_opt(ts_millis).unwrap();
+
+                        Timestamp::Text(zone.format_instant(utc, &self.time_format))
+                    }
+
+                    Err(_) => Timestamp::Millis(ts_millis),
+                }
+            }
+        }
+    }
 }
 
 ///
@@ -182,3 +571,153 @@ impl Options {
 pub fn parse() -> Options {
     Options::parse()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// テスト用の共有バッファ書き込み先（`finish`で消費された後も内容を参照
+    /// できるように`Rc<RefCell<_>>`で保持する）
+    #[derive(Clone)]
+    struct SharedBuf(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn output_writer_plain_passes_bytes_through() {
+        let buf = Rc::new(RefCell::new(Vec::new()));
+        let mut writer = OutputWriter::Plain(Box::new(SharedBuf(buf.clone())));
+
+        writer.write_all(b"hello").unwrap();
+        writer.finish().unwrap();
+
+        assert_eq!(&buf.borrow()[..], b"hello");
+    }
+
+    #[test]
+    fn output_writer_gzip_round_trips_through_gzdecoder() {
+        use std::io::Read;
+
+        let buf = Rc::new(RefCell::new(Vec::new()));
+        let sink: Box<dyn Write> = Box::new(SharedBuf(buf.clone()));
+        let mut writer = OutputWriter::Gzip(GzEncoder::new(sink, Compression::default()));
+
+        writer.write_all(b"hello, gzip").unwrap();
+        writer.finish().unwrap();
+
+        let compressed = buf.borrow().clone();
+        let mut decoder = flate2::read::GzDecoder::new(&compressed[..]);
+        let mut decoded = String::new();
+        decoder.read_to_string(&mut decoded).unwrap();
+
+        assert_eq!(decoded, "hello, gzip");
+    }
+
+    #[test]
+    fn parse_fixed_offset_accepts_common_forms() {
+        assert_eq!(parse_fixed_offset("+09:00"), FixedOffset::east_opt(9 * 3600));
+        assert_eq!(
+            parse_fixed_offset("-0530"),
+            FixedOffset::east_opt(-(5 * 3600 + 30 * 60))
+        );
+        assert_eq!(parse_fixed_offset("UTC+09:00"), FixedOffset::east_opt(9 * 3600));
+        assert_eq!(parse_fixed_offset("GMT-5"), FixedOffset::east_opt(-5 * 3600));
+    }
+
+    #[test]
+    fn parse_fixed_offset_rejects_unknown_forms() {
+        assert_eq!(parse_fixed_offset("Asia/Tokyo"), None);
+        assert_eq!(parse_fixed_offset(""), None);
+    }
+
+    #[test]
+    fn posix_tz_std_offset_inverts_sign_east_of_utc() {
+        // JST-9: 標準時はUTCから東へ9時間（UTC+9）
+        assert_eq!(posix_tz_std_offset("JST-9"), FixedOffset::east_opt(9 * 3600));
+    }
+
+    #[test]
+    fn posix_tz_std_offset_inverts_sign_west_of_utc() {
+        // CST6CDT,...: 標準時はUTCから西へ6時間（UTC-6）
+        assert_eq!(
+            posix_tz_std_offset("CST6CDT,M3.2.0,M11.1.0"),
+            FixedOffset::east_opt(-6 * 3600)
+        );
+    }
+
+    #[test]
+    fn posix_tz_std_offset_handles_zero_and_bracket_names() {
+        assert_eq!(posix_tz_std_offset("UTC0"), FixedOffset::east_opt(0));
+        assert_eq!(posix_tz_std_offset("<+09>-9"), FixedOffset::east_opt(9 * 3600));
+    }
+
+    #[test]
+    fn local_to_unix_millis_errors_on_dst_gap() {
+        // 2024-03-10 02:30 America/New_Yorkは夏時間開始による欠落時間帯
+        let zone = ZoneInfo::Named(chrono_tz::America::New_York);
+        let time = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap()
+            .and_hms_opt(2, 30, 0).unwrap();
+
+        assert_eq!(zone.local_to_unix_millis(time, "test"), Err(()));
+    }
+
+    #[test]
+    fn format_timestamp_renders_rfc3339_with_resolved_offset() {
+        let opts = Options::parse_from(["converter", "--time-format", "rfc3339", "dummy.dat"]);
+        let zone = ZoneInfo::Fixed(FixedOffset::east_opt(9 * 3600).unwrap());
+
+        match opts.format_timestamp(1_700_000_000_000, Ok(&zone)) {
+            Timestamp::Text(s) => assert_eq!(s, "2023-11-15T07:13:20.000+09:00"),
+            _ => panic!("expected Timestamp::Text"),
+        }
+    }
+
+    #[test]
+    fn format_timestamp_renders_local_with_resolved_offset() {
+        let opts = Options::parse_from(["converter", "--time-format", "local", "dummy.dat"]);
+        let zone = ZoneInfo::Fixed(FixedOffset::east_opt(9 * 3600).unwrap());
+
+        match opts.format_timestamp(1_700_000_000_000, Ok(&zone)) {
+            Timestamp::Text(s) => assert_eq!(s, "2023-11-15 07:13:20.000 +09:00"),
+            _ => panic!("expected Timestamp::Text"),
+        }
+    }
+
+    #[test]
+    fn format_timestamp_falls_back_to_epoch_ms_when_zone_unresolved() {
+        let opts = Options::parse_from(["converter", "--time-format", "rfc3339", "dummy.dat"]);
+
+        match opts.format_timestamp(1_700_000_000_000, Err(())) {
+            Timestamp::Millis(ms) => assert_eq!(ms, 1_700_000_000_000),
+            _ => panic!("expected Timestamp::Millis"),
+        }
+    }
+
+    #[test]
+    fn local_to_unix_millis_picks_earliest_on_ambiguous_time() {
+        // 2024-11-03 01:30 America/New_Yorkは夏時間終了による重複時間帯
+        // （最初の候補はEDT=UTC-4）
+        let zone = ZoneInfo::Named(chrono_tz::America::New_York);
+        let time = NaiveDate::from_ymd_opt(2024, 11, 3).unwrap()
+            .and_hms_opt(1, 30, 0).unwrap();
+
+        let expected = NaiveDate::from_ymd_opt(2024, 11, 3).unwrap()
+            .and_hms_opt(5, 30, 0).unwrap()
+            .and_utc()
+            .timestamp_millis();
+
+        assert_eq!(zone.local_to_unix_millis(time, "test"), Ok(expected));
+    }
+}
@@ -0,0 +1,376 @@
+/*
+ * output format encoders for the log data converter
+ *
+ * Copyright (C) 2024 Hiroshi Kuwagata <kgt9221@gmail.com>
+ */
+
+///
+/// 出力フォーマットエンコーダモジュール
+///
+
+use std::io::{self, Write};
+
+/// 出力フォーマットの種別
+#[derive(clap::ValueEnum, Clone, Debug)]
+pub enum Format {
+    /// CSV形式
+    Csv,
+
+    /// JSON形式（全エントリを配列でラップ）
+    Json,
+
+    /// NDJSON形式（1行に1エントリのJSONオブジェクト）
+    Ndjson,
+}
+
+/// エンコーダに渡すタイムスタンプ値
+pub enum Timestamp {
+    /// ミリ秒単位のUNIX時刻（数値としてそのまま出力する）
+    Millis(i64),
+
+    /// 整形済みの時刻文字列（rfc3339/local指定時）
+    Text(String),
+}
+
+///
+/// 出力フォーマットエンコーダが実装すべきトレイト
+///
+pub trait Encoder {
+    ///
+    /// ヘッダ（あるいは先頭部）の出力
+    ///
+    /// # 引数
+    /// `out` - 出力先
+    ///
+    fn write_header(&mut self, out: &mut dyn Write) -> io::Result<()>;
+
+    ///
+    /// 1エントリ分の出力
+    ///
+    /// # 引数
+    /// `out` - 出力先
+    /// `ts` - タイムスタンプ（記録開始時刻を加味した値）
+    /// `v` - 電圧値(V)
+    /// `i` - 電流値(mA)
+    /// `p` - 瞬間電力値(W)。`--power`指定時のみSome()でラップされる。
+    ///
+    fn write_entry(
+        &mut self, out: &mut dyn Write, ts: Timestamp, v: f32, i: f32, p: Option<f32>
+    ) -> io::Result<()>;
+
+    ///
+    /// 出力の終了処理（末尾の出力や後始末）
+    ///
+    /// # 引数
+    /// `out` - 出力先
+    ///
+    fn finish(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        let _ = out;
+        Ok(())
+    }
+}
+
+/// CSV形式エンコーダ
+pub struct CsvEncoder {
+    /// 電力列を出力するかどうか
+    power: bool,
+}
+
+impl CsvEncoder {
+    ///
+    /// コンストラクタ
+    ///
+    /// # 引数
+    /// `power` - 電力列を出力するかどうか
+    ///
+    pub fn new(power: bool) -> Self {
+        Self { power }
+    }
+}
+
+impl Encoder for CsvEncoder {
+    fn write_header(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        let bom: &[u8] = b"\xef\xbb\xbf";
+
+        let header = if self.power {
+            "\"timestamp\",\"voltage\",\"current\",\"power\"\n"
+        } else {
+            "\"timestamp\",\"voltage\",\"current\"\n"
+        };
+
+        // Excelでの文字化けを避けるためにBOMを出力
+        out.write_all(bom)?;
+
+        // ヘッダを出力
+        out.write_all(header.as_bytes())?;
+
+        Ok(())
+    }
+
+    fn write_entry(
+        &mut self, out: &mut dyn Write, ts: Timestamp, v: f32, i: f32, p: Option<f32>
+    ) -> io::Result<()> {
+        let s = match p {
+            Some(p) => format!(
+                "{},{:.5},{:.1},{:.3}\n", ts_to_csv_field(&ts), v, i, p
+            ),
+
+            None => format!("{},{:.5},{:.1}\n", ts_to_csv_field(&ts), v, i),
+        };
+
+        out.write_all(s.as_bytes())
+    }
+}
+
+///
+/// タイムスタンプのCSVフィールドへの変換
+///
+/// # 引数
+/// `ts` - タイムスタンプ
+///
+/// # 戻り値
+/// CSVの1フィールドとして出力する文字列
+///
+fn ts_to_csv_field(ts: &Timestamp) -> String {
+    match ts {
+        Timestamp::Millis(ms) => ms.to_string(),
+        Timestamp::Text(s) => s.clone(),
+    }
+}
+
+/// JSON形式エンコーダ（全エントリを配列でラップし、先頭にメタデータを置く）
+pub struct JsonEncoder {
+    /// 記録開始時刻（ミリ秒単位のUNIX時刻）
+    start_time: i64,
+
+    /// 適用したタイムゾーン文字列
+    timezone: String,
+}
+
+impl JsonEncoder {
+    ///
+    /// コンストラクタ
+    ///
+    /// # 引数
+    /// `start_time` - 記録開始時刻（ミリ秒単位のUNIX時刻）
+    /// `timezone` - 適用したタイムゾーン文字列
+    ///
+    pub fn new(start_time: i64, timezone: String) -> Self {
+        Self {
+            start_time,
+            timezone,
+        }
+    }
+}
+
+impl Encoder for JsonEncoder {
+    fn write_header(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        let s = format!(
+            "[\n{{\"meta\":{{\"start\":{},\"timezone\":\"{}\"}}}}",
+            self.start_time, json_escape(&self.timezone)
+        );
+
+        out.write_all(s.as_bytes())
+    }
+
+    fn write_entry(
+        &mut self, out: &mut dyn Write, ts: Timestamp, v: f32, i: f32, p: Option<f32>
+    ) -> io::Result<()> {
+        let s = format!(
+            ",\n{{\"timestamp\":{},\"voltage\":{:.5},\"current\":{:.1}{}}}",
+            ts_to_json_field(&ts), v, i, power_to_json_suffix(p)
+        );
+
+        out.write_all(s.as_bytes())
+    }
+
+    fn finish(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        out.write_all(b"\n]\n")
+    }
+}
+
+/// NDJSON形式エンコーダ（1行に1エントリのJSONオブジェクト）
+pub struct NdjsonEncoder;
+
+impl Encoder for NdjsonEncoder {
+    fn write_header(&mut self, _out: &mut dyn Write) -> io::Result<()> {
+        // NDJSONにヘッダは存在しない
+        Ok(())
+    }
+
+    fn write_entry(
+        &mut self, out: &mut dyn Write, ts: Timestamp, v: f32, i: f32, p: Option<f32>
+    ) -> io::Result<()> {
+        let s = format!(
+            "{{\"timestamp\":{},\"voltage\":{:.5},\"current\":{:.1}{}}}\n",
+            ts_to_json_field(&ts), v, i, power_to_json_suffix(p)
+        );
+
+        out.write_all(s.as_bytes())
+    }
+}
+
+///
+/// タイムスタンプのJSONフィールドへの変換
+///
+/// # 引数
+/// `ts` - タイムスタンプ
+///
+/// # 戻り値
+/// JSONの値として出力する文字列（数値はそのまま、文字列は引用符で囲む）
+///
+fn ts_to_json_field(ts: &Timestamp) -> String {
+    match ts {
+        Timestamp::Millis(ms) => ms.to_string(),
+        Timestamp::Text(s) => format!("\"{}\"", s),
+    }
+}
+
+///
+/// JSON文字列値としてのエスケープ
+///
+/// # 引数
+/// `s` - エスケープ対象の文字列
+///
+/// # 戻り値
+/// `"`・`\`・制御文字をJSON文字列リテラルとして安全な形にエスケープした文字
+/// 列（前後の引用符は含まない）
+///
+fn json_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+///
+/// 電力値のJSONオブジェクトへの追記文字列の生成
+///
+/// # 引数
+/// `p` - 電力値(W)
+///
+/// # 戻り値
+/// `p`がSome()の場合は`,"power":<値>`を、Noneの場合は空文字列を返す。
+///
+fn power_to_json_suffix(p: Option<f32>) -> String {
+    match p {
+        Some(p) => format!(",\"power\":{:.3}", p),
+        None => String::new(),
+    }
+}
+
+///
+/// フォーマット種別に対応するエンコーダの生成
+///
+/// # 引数
+/// `format` - 出力フォーマットの種別
+/// `start_time` - 記録開始時刻（ミリ秒単位のUNIX時刻）
+/// `timezone` - 適用したタイムゾーン文字列
+/// `power` - 電力列を出力するかどうか（CSVのヘッダ生成にのみ影響する）
+///
+/// # 戻り値
+/// 指定されたフォーマットに対応するEncoderトレイトオブジェクト
+///
+pub fn new_encoder(format: &Format, start_time: i64, timezone: String, power: bool)
+    -> Box<dyn Encoder>
+{
+    match format {
+        Format::Csv => Box::new(CsvEncoder::new(power)),
+        Format::Json => Box::new(JsonEncoder::new(start_time, timezone)),
+        Format::Ndjson => Box::new(NdjsonEncoder),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_encoder_writes_bom_and_header_without_power() {
+        let mut out = Vec::new();
+        let mut enc = CsvEncoder::new(false);
+
+        enc.write_header(&mut out).unwrap();
+        enc.write_entry(&mut out, Timestamp::Millis(1000), 5.0, 120.0, None).unwrap();
+
+        let s = String::from_utf8(out).unwrap();
+        assert_eq!(s, "\u{feff}\"timestamp\",\"voltage\",\"current\"\n1000,5.00000,120.0\n");
+    }
+
+    #[test]
+    fn csv_encoder_adds_power_column_when_enabled() {
+        let mut out = Vec::new();
+        let mut enc = CsvEncoder::new(true);
+
+        enc.write_header(&mut out).unwrap();
+        enc.write_entry(&mut out, Timestamp::Millis(1000), 5.0, 120.0, Some(0.6)).unwrap();
+
+        let s = String::from_utf8(out).unwrap();
+        assert_eq!(
+            s,
+            "\u{feff}\"timestamp\",\"voltage\",\"current\",\"power\"\n1000,5.00000,120.0,0.600\n"
+        );
+    }
+
+    #[test]
+    fn json_encoder_wraps_entries_in_an_array() {
+        let mut out = Vec::new();
+        let mut enc = JsonEncoder::new(1000, "Asia/Tokyo".to_string());
+
+        enc.write_header(&mut out).unwrap();
+        enc.write_entry(&mut out, Timestamp::Millis(1000), 5.0, 120.0, None).unwrap();
+        enc.finish(&mut out).unwrap();
+
+        let s = String::from_utf8(out).unwrap();
+        assert_eq!(
+            s,
+            "[\n{\"meta\":{\"start\":1000,\"timezone\":\"Asia/Tokyo\"}}\
+             ,\n{\"timestamp\":1000,\"voltage\":5.00000,\"current\":120.0}\n]\n"
+        );
+    }
+
+    #[test]
+    fn ndjson_encoder_writes_one_object_per_line() {
+        let mut out = Vec::new();
+        let mut enc = NdjsonEncoder;
+
+        enc.write_header(&mut out).unwrap();
+        enc.write_entry(&mut out, Timestamp::Millis(1000), 5.0, 120.0, None).unwrap();
+        enc.write_entry(&mut out, Timestamp::Millis(2000), 5.1, 121.0, None).unwrap();
+
+        let s = String::from_utf8(out).unwrap();
+        assert_eq!(
+            s,
+            "{\"timestamp\":1000,\"voltage\":5.00000,\"current\":120.0}\n\
+             {\"timestamp\":2000,\"voltage\":5.10000,\"current\":121.0}\n"
+        );
+    }
+
+    #[test]
+    fn json_escape_escapes_quotes_and_backslashes() {
+        assert_eq!(json_escape("Asia/Tokyo\"x"), "Asia/Tokyo\\\"x");
+        assert_eq!(json_escape("a\\b"), "a\\\\b");
+    }
+
+    #[test]
+    fn ndjson_encoder_adds_power_field_when_enabled() {
+        let mut out = Vec::new();
+        let mut enc = NdjsonEncoder;
+
+        enc.write_entry(&mut out, Timestamp::Millis(1000), 5.0, 120.0, Some(0.6)).unwrap();
+
+        let s = String::from_utf8(out).unwrap();
+        assert_eq!(s, "{\"timestamp\":1000,\"voltage\":5.00000,\"current\":120.0,\"power\":0.600}\n");
+    }
+}
@@ -8,10 +8,11 @@
 /// プログラムのエントリポイント
 ///
 
-use std::io::{Write, Error, ErrorKind};
+use std::io::{Error, ErrorKind};
 use binrw::BinRead;
 
 pub mod cmd_args;
+pub mod encoder;
 
 /// データのエントリ（1エントリ文）を格納する構造体。
 #[derive(BinRead, Debug)]
@@ -53,22 +54,123 @@ impl Entry {
     fn get_current(&self) -> f32 {
         (self.current as f32) * Self::CURRENT_COEFFICIENT
     }
+
+    ///
+    /// 瞬間電力値(W)の取得
+    ///
+    /// # 戻り値
+    /// 電圧値(V)と電流値(mA)から算出した瞬間電力値をWに変換した値を返す。
+    ///
+    fn get_power(&self) -> f32 {
+        self.get_voltage() * self.get_current() / 1000.0
+    }
 }
 
 ///
-/// ヘッダの出力
+/// 集計統計を求めるための累積器
+///
+/// 電圧・電流の最小値/最大値/平均値と、台形則による積算電力量を求める。
 ///
-fn write_header(output: &mut dyn Write) -> std::io::Result<()> {
-    let bom: &[u8] =  b"\xef\xbb\xbf";
-    let header: &[u8] = "\"timestamp\",\"voltage\",\"current\"\n".as_bytes();
+struct Summary {
+    /// 集計したエントリ数
+    count: u64,
 
-    // Excelでの文字化けを避けるためにBOMを出力
-    output.write_all(bom)?;
+    /// 電圧の最小値(V)
+    v_min: f32,
 
-    // ヘッダを出力
-    output.write_all(header)?;
+    /// 電圧の最大値(V)
+    v_max: f32,
+
+    /// 電圧の合計値(V)
+    v_sum: f64,
+
+    /// 電流の最小値(mA)
+    i_min: f32,
+
+    /// 電流の最大値(mA)
+    i_max: f32,
+
+    /// 電流の合計値(mA)
+    i_sum: f64,
+
+    /// 積算した電力量(J)
+    energy_j: f64,
+
+    /// 直前のエントリの（記録開始時刻を加味したミリ秒単位の時刻, 瞬間電力値）
+    prev: Option<(i64, f32)>,
+}
+
+impl Summary {
+    ///
+    /// コンストラクタ
+    ///
+    fn new() -> Self {
+        Self {
+            count: 0,
+            v_min: f32::MAX,
+            v_max: f32::MIN,
+            v_sum: 0.0,
+            i_min: f32::MAX,
+            i_max: f32::MIN,
+            i_sum: 0.0,
+            energy_j: 0.0,
+            prev: None,
+        }
+    }
+
+    ///
+    /// エントリの追加
+    ///
+    /// # 引数
+    /// `ts_millis` - 記録開始時刻を加味したミリ秒単位の時刻
+    /// `v` - 電圧値(V)
+    /// `i` - 電流値(mA)
+    /// `p` - 瞬間電力値(W)
+    ///
+    fn add(&mut self, ts_millis: i64, v: f32, i: f32, p: f32) {
+        self.count += 1;
+        self.v_min = self.v_min.min(v);
+        self.v_max = self.v_max.max(v);
+        self.v_sum += v as f64;
+        self.i_min = self.i_min.min(i);
+        self.i_max = self.i_max.max(i);
+        self.i_sum += i as f64;
+
+        // 台形則により直前のエントリとの間の電力量を積算する
+        if let Some((prev_ts, prev_p)) = self.prev {
+            let dt_sec = ((ts_millis - prev_ts) as f64) / 1000.0;
+
+            self.energy_j += 0.5 * ((prev_p + p) as f64) * dt_sec;
+        }
+
+        self.prev = Some((ts_millis, p));
+    }
+
+    ///
+    /// 集計結果のstderrへの出力
+    ///
+    fn report(&self) {
+        if self.count == 0 {
+            eprintln!("Summary: no entries");
+            return;
+        }
 
-    Ok(())
+        let v_mean = self.v_sum / (self.count as f64);
+        let i_mean = self.i_sum / (self.count as f64);
+        let energy_mwh = self.energy_j / 3.6;
+
+        eprintln!("Summary:");
+        eprintln!("  entries: {}", self.count);
+        eprintln!(
+            "  voltage(V): min={:.5} max={:.5} mean={:.5}",
+            self.v_min, self.v_max, v_mean
+        );
+        eprintln!(
+            "  current(mA): min={:.1} max={:.1} mean={:.1}",
+            self.i_min, self.i_max, i_mean
+        );
+        eprintln!("  energy: {:.3} mWh", energy_mwh);
+    }
 }
 
 ///
@@ -89,9 +191,15 @@ fn io_error(err: &dyn ToString) -> Error {
 ///
 fn run() -> std::io::Result<()> {
     let opts = cmd_args::parse();
-    let ts_offset = opts.get_record_time().unwrap_or(0);
+
+    // タイムゾーンの解決はここで一度だけ行い、以降はその結果を使い回す
+    let zone = opts.resolve_timezone();
+    let zone_ref = zone.as_ref().map_err(|_| ());
+
+    let ts_offset = opts.get_record_time(zone_ref).unwrap_or(0);
     let mut flag = false;
     let mut ts0 = 0;
+    let mut summary = Summary::new();
 
     // 入力元を取得
     let mut input = opts.get_input_file()?;
@@ -99,10 +207,13 @@ fn run() -> std::io::Result<()> {
     // 出力先を取得
     let mut output = opts.get_output_writer()?;
 
+    // 出力フォーマットエンコーダを取得
+    let mut encoder = opts.get_encoder(ts_offset);
+
     // ヘッダを出力
-    write_header(&mut output)?;
+    encoder.write_header(&mut output)?;
 
-    // 1エントリづつ読みこみCSVに変換して出力
+    // 1エントリづつ読みこみ指定フォーマットに変換して出力
     loop {
         match Entry::read(&mut input) {
             Ok(entry) => {
@@ -111,14 +222,19 @@ fn run() -> std::io::Result<()> {
                     flag = true;
                 }
 
-                let s = format!(
-                    "{},{:.5},{:.1}\n",
-                    ts_offset + ((entry.timestamp as i64) - ts0),
-                    entry.get_voltage(),
-                    entry.get_current()
-                );
+                let ts_millis = ts_offset + ((entry.timestamp as i64) - ts0);
+                let ts = opts.format_timestamp(ts_millis, zone_ref);
+                let v = entry.get_voltage();
+                let i = entry.get_current();
+                let p = entry.get_power();
+
+                if opts.summary_enabled() {
+                    summary.add(ts_millis, v, i, p);
+                }
+
+                let p = if opts.power_enabled() { Some(p) } else { None };
 
-                output.write_all(s.as_bytes())?;
+                encoder.write_entry(&mut output, ts, v, i, p)?;
             }
 
             Err(err) => {
@@ -131,6 +247,15 @@ fn run() -> std::io::Result<()> {
         }
     }
 
+    encoder.finish(&mut output)?;
+
+    // gzip圧縮している場合はトレイラーの書き込みを含めて出力を終了させる
+    output.finish()?;
+
+    if opts.summary_enabled() {
+        summary.report();
+    }
+
     return Ok(());
 }
 
@@ -143,3 +268,27 @@ fn main() {
         std::process::exit(1);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_add_tracks_min_max_mean_and_trapezoidal_energy() {
+        let mut summary = Summary::new();
+
+        summary.add(0, 5.0, 100.0, 0.5);
+        summary.add(1000, 5.0, 100.0, 1.5);
+        summary.add(3000, 5.0, 100.0, 0.5);
+
+        assert_eq!(summary.count, 3);
+        assert_eq!(summary.v_min, 5.0);
+        assert_eq!(summary.v_max, 5.0);
+        assert_eq!(summary.i_min, 100.0);
+        assert_eq!(summary.i_max, 100.0);
+
+        // 台形則: [0,1000)ms区間は0.5*(0.5+1.5)*1.0=1.0J、
+        // [1000,3000)ms区間は0.5*(1.5+0.5)*2.0=2.0Jで合計3.0J
+        assert!((summary.energy_j - 3.0).abs() < 1e-9);
+    }
+}